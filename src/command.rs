@@ -1,14 +1,15 @@
 use crate::connection::{Connection, Protocol};
 use crate::frame::{number_from_binary, string_from_binary, Frame};
-use crate::snowflake::next_id;
+use crate::snowflake::{next_id, next_ids};
 use std::io::{ErrorKind, Result};
+use tokio::io::{AsyncRead, AsyncWrite};
 
 #[derive(Debug)]
 pub enum Command {
-    AUTH,
+    AUTH(String),
     CLIENT,
-    HELLO(Option<u8>),
-    NEXT,
+    HELLO(Option<u8>, Option<String>),
+    NEXT(Option<u16>),
     SELECT,
     UNKNOWN(String),
 }
@@ -23,24 +24,51 @@ impl Command {
                     match &frames[0] {
                         Frame::BulkString(b) => string_from_binary(b)
                             .and_then(|s| match s.to_ascii_lowercase().as_str() {
-                                "auth" => Ok(Command::AUTH),
+                                "auth" => match frames.last() {
+                                    Some(Frame::BulkString(password)) if frames.len() > 1 => {
+                                        Ok(Command::AUTH(string_from_binary(password)?))
+                                    }
+                                    _ => Ok(Command::UNKNOWN("wrong number of arguments for 'auth' command".to_string())),
+                                },
                                 "client" => Ok(Command::CLIENT),
                                 "hello" => {
-                                    if frames.len() > 1 {
+                                    let protocol_version = if frames.len() > 1 {
                                         match &frames[1] {
                                             Frame::BulkString(protocol) => {
                                                 match number_from_binary(protocol) {
-                                                    Ok(version) => Ok(Command::HELLO(Some(version))),
-                                                    Err(_) => Ok(Command::UNKNOWN("Protocol version is not an integer or out of range".to_string())),
+                                                    Ok(version) => Some(version),
+                                                    Err(_) => return Ok(Command::UNKNOWN("Protocol version is not an integer or out of range".to_string())),
                                                 }
                                             }
-                                            _ => Ok(Command::UNKNOWN("Protocol version is not an integer or out of range".to_string())),
+                                            _ => return Ok(Command::UNKNOWN("Protocol version is not an integer or out of range".to_string())),
+                                        }
+                                    } else {
+                                        None
+                                    };
+                                    // the password follows the literal "AUTH" token as `AUTH user pass`
+                                    let password = frames
+                                        .iter()
+                                        .position(|f| matches!(f, Frame::BulkString(b) if string_from_binary(b).map(|s| s.eq_ignore_ascii_case("auth")).unwrap_or(false)))
+                                        .and_then(|pos| frames.get(pos + 2))
+                                        .and_then(|f| match f {
+                                            Frame::BulkString(b) => string_from_binary(b).ok(),
+                                            _ => None,
+                                        });
+                                    Ok(Command::HELLO(protocol_version, password))
+                                }
+                                "next" => {
+                                    if frames.len() > 1 {
+                                        match &frames[1] {
+                                            Frame::BulkString(count) => match number_from_binary(count) {
+                                                Ok(count) => Ok(Command::NEXT(Some(count))),
+                                                Err(_) => Ok(Command::UNKNOWN("value is not an integer or out of range".to_string())),
+                                            },
+                                            _ => Ok(Command::UNKNOWN("value is not an integer or out of range".to_string())),
                                         }
                                     } else {
-                                        Ok(Command::HELLO(None))
+                                        Ok(Command::NEXT(None))
                                     }
                                 }
-                                "next" => Ok(Command::NEXT),
                                 "select" => Ok(Command::SELECT),
                                 _ => Ok(Command::UNKNOWN("Unknown command".to_string())),
                             }),
@@ -52,42 +80,82 @@ impl Command {
         }
     }
 
-    pub async fn apply(self, conn: &mut Connection) -> Frame {
+    pub async fn apply<S: AsyncRead + AsyncWrite + Unpin>(self, conn: &mut Connection<S>) -> Frame {
         match self {
-            Command::AUTH => Frame::SimpleString("OK".to_string()),
+            Command::AUTH(password) => match conn.authenticate(&password) {
+                Ok(Some(salt)) => Frame::Array(vec![
+                    Frame::SimpleString("OK".to_string()),
+                    Frame::BulkString(salt.to_vec()),
+                ]),
+                Ok(None) => Frame::SimpleString("OK".to_string()),
+                Err(()) => Frame::SimpleError("ERR invalid password".to_string()),
+            },
             Command::CLIENT => Frame::SimpleString("OK".to_string()),
-            Command::HELLO(protocol_version) => {
+            Command::HELLO(protocol_version, password) => {
                 if let Some(version) = protocol_version {
                     match Protocol::try_from(version) {
                         Ok(protocol) => conn.protocol = protocol,
                         Err(error) => return Frame::SimpleError(error),
                     }
                 }
+                let salt = match password {
+                    Some(password) => match conn.authenticate(&password) {
+                        Ok(salt) => salt,
+                        Err(()) => return Frame::SimpleError("ERR invalid password".to_string()),
+                    },
+                    None => None,
+                };
                 match conn.protocol {
-                    Protocol::RESP2 => Frame::Array(vec![
-                        Frame::SimpleString("server".to_string()),
-                        Frame::SimpleString(env!("CARGO_PKG_NAME").to_string()),
-                        Frame::SimpleString("version".to_string()),
-                        Frame::SimpleString(env!("CARGO_PKG_VERSION").to_string()),
-                        Frame::SimpleString("proto".to_string()),
-                        Frame::Integer(2),
-                    ]),
-                    Protocol::RESP3 => Frame::Map(vec![
-                        (
+                    Protocol::RESP2 => {
+                        let mut fields = vec![
                             Frame::SimpleString("server".to_string()),
                             Frame::SimpleString(env!("CARGO_PKG_NAME").to_string()),
-                        ),
-                        (
                             Frame::SimpleString("version".to_string()),
                             Frame::SimpleString(env!("CARGO_PKG_VERSION").to_string()),
-                        ),
-                        (Frame::SimpleString("proto".to_string()), Frame::Integer(3)),
-                    ]),
+                            Frame::SimpleString("proto".to_string()),
+                            Frame::Integer(2),
+                        ];
+                        if let Some(salt) = salt {
+                            fields.push(Frame::SimpleString("salt".to_string()));
+                            fields.push(Frame::BulkString(salt.to_vec()));
+                        }
+                        Frame::Array(fields)
+                    }
+                    Protocol::RESP3 => {
+                        let mut fields = vec![
+                            (
+                                Frame::SimpleString("server".to_string()),
+                                Frame::SimpleString(env!("CARGO_PKG_NAME").to_string()),
+                            ),
+                            (
+                                Frame::SimpleString("version".to_string()),
+                                Frame::SimpleString(env!("CARGO_PKG_VERSION").to_string()),
+                            ),
+                            (Frame::SimpleString("proto".to_string()), Frame::Integer(3)),
+                        ];
+                        if let Some(salt) = salt {
+                            fields.push((
+                                Frame::SimpleString("salt".to_string()),
+                                Frame::BulkString(salt.to_vec()),
+                            ));
+                        }
+                        Frame::Map(fields)
+                    }
+                }
+            }
+            Command::NEXT(count) => {
+                if !conn.authenticated {
+                    return Frame::SimpleError("NOAUTH Authentication required.".to_string());
+                }
+                match count {
+                    None => next_id()
+                        .map(|id| Frame::Integer(id))
+                        .unwrap_or_else(|err| Frame::SimpleError(err)),
+                    Some(count) => next_ids(count)
+                        .map(|ids| Frame::Array(ids.into_iter().map(Frame::Integer).collect()))
+                        .unwrap_or_else(|err| Frame::SimpleError(err)),
                 }
             }
-            Command::NEXT => next_id()
-                .map(|id| Frame::Integer(id))
-                .unwrap_or_else(|err| Frame::SimpleError(err)),
             Command::SELECT => Frame::SimpleString("OK".to_string()),
             Command::UNKNOWN(error) => Frame::SimpleError(format!("ERR {}", error)),
         }
@@ -1,13 +1,21 @@
+use crate::crypto::{Cipher, SALT_LEN};
 use crate::frame::Frame;
 use bytes::{Buf, BytesMut};
 use std::io::{Cursor, ErrorKind, Result};
 use std::net::SocketAddr;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
-use tokio::net::TcpStream;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 use tracing::info;
 
 const BUFFER_SIZE: usize = 128;
 
+/// Upper bound on how large the read buffer (or a single AEAD record) may
+/// grow while waiting for a frame to complete. The buffer itself is
+/// otherwise unbounded to support pipelining, so this is what stops a
+/// client from claiming an enormous bulk-string length and forcing
+/// unbounded memory growth before any command, let alone `AUTH`, runs.
+const MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
 #[derive(Debug)]
 pub enum Protocol {
     RESP2,
@@ -28,31 +36,68 @@ impl TryFrom<u8> for Protocol {
     }
 }
 
+/// Wraps any byte stream (plain TCP, a WebSocket adapter, TLS, ...) and
+/// speaks the RESP frame protocol over it, so `Handler` never needs to know
+/// which transport a client connected through.
 #[derive(Debug)]
-pub struct Connection {
+pub struct Connection<S> {
     addr: SocketAddr,
-    stream: BufWriter<TcpStream>,
+    stream: BufWriter<S>,
     buffer: BytesMut,
     pub protocol: Protocol,
+    requirepass: Option<Arc<str>>,
+    pub authenticated: bool,
+    cipher: Option<Cipher>,
 }
 
-impl Connection {
-    pub fn new(socket: TcpStream, addr: SocketAddr) -> Connection {
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    pub fn new(stream: S, addr: SocketAddr, requirepass: Option<Arc<str>>) -> Connection<S> {
+        let authenticated = requirepass.is_none();
         Connection {
-            stream: BufWriter::new(socket),
+            stream: BufWriter::new(stream),
             addr,
             buffer: BytesMut::with_capacity(BUFFER_SIZE),
             protocol: Protocol::RESP2,
+            requirepass,
+            authenticated,
+            cipher: None,
+        }
+    }
+
+    /// Checks `password` against `--requirepass` in constant time. On a
+    /// match, marks the connection authenticated and engages the
+    /// AEAD-encrypted framing layer for the rest of the connection, keyed
+    /// off a freshly generated salt returned as `Ok(Some(salt))`. The caller
+    /// must hand that salt back to the client so it can derive the same
+    /// key. `Ok(None)` means the connection authenticated without engaging
+    /// encryption (no `--requirepass` configured); `Err(())` means the
+    /// password was wrong.
+    pub fn authenticate(&mut self, password: &str) -> std::result::Result<Option<[u8; SALT_LEN]>, ()> {
+        match &self.requirepass {
+            Some(secret) => {
+                if constant_time_eq(secret.as_bytes(), password.as_bytes()) {
+                    self.authenticated = true;
+                    let salt = Cipher::random_salt();
+                    self.cipher = Some(Cipher::new(secret, &salt));
+                    Ok(Some(salt))
+                } else {
+                    Err(())
+                }
+            }
+            None => Ok(None),
         }
     }
 
+    /// Reads a single frame, pulling more bytes off the transport as needed.
+    /// Any additional pipelined frames left over in the buffer afterwards
+    /// can be drained without further I/O via `try_parse_frame`.
     pub async fn read_frame(&mut self) -> Result<Option<Frame>> {
         info!("Reading data from {}", self.addr);
         loop {
-            if let Some(frame) = self.parse_frame()? {
+            if let Some(frame) = self.try_parse_frame()? {
                 return Ok(Some(frame));
             }
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
+            if !self.fill_buffer().await? {
                 info!("Client from {} closed", self.addr);
                 return if self.buffer.is_empty() {
                     Ok(None)
@@ -63,7 +108,55 @@ impl Connection {
         }
     }
 
-    fn parse_frame(&mut self) -> Result<Option<Frame>> {
+    /// Pulls more bytes from the transport into the buffer, transparently
+    /// decrypting a sealed record if the AEAD layer is engaged. Returns
+    /// `false` on a clean EOF.
+    async fn fill_buffer(&mut self) -> Result<bool> {
+        let got_more = if self.cipher.is_some() {
+            match self.read_sealed_record().await? {
+                Some(plaintext) => {
+                    self.buffer.extend_from_slice(&plaintext);
+                    true
+                }
+                None => false,
+            }
+        } else {
+            0 != self.stream.read_buf(&mut self.buffer).await?
+        };
+        if self.buffer.len() > MAX_BUFFER_SIZE {
+            self.buffer.clear();
+            return Err(ErrorKind::InvalidData.into());
+        }
+        Ok(got_more)
+    }
+
+    /// Reads one length-prefixed AEAD record and decrypts it into plaintext
+    /// RESP bytes, or `None` on a clean EOF before the next record starts.
+    async fn read_sealed_record(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut len_bytes = [0u8; 4];
+        match self.stream.read_exact(&mut len_bytes).await {
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        if len > MAX_BUFFER_SIZE {
+            return Err(ErrorKind::InvalidData.into());
+        }
+        let mut record = vec![0u8; len];
+        self.stream.read_exact(&mut record).await?;
+        self.cipher
+            .as_mut()
+            .expect("read_sealed_record is only called once the cipher is engaged")
+            .open(&record)
+            .map(Some)
+    }
+
+    /// Attempts to parse a single complete frame out of already-buffered
+    /// bytes without performing any I/O. Returns `None` if the buffer only
+    /// holds an incomplete frame so far, letting the caller drain every
+    /// pipelined command currently buffered before awaiting the socket again.
+    pub fn try_parse_frame(&mut self) -> Result<Option<Frame>> {
         let mut cursor = Cursor::new(&self.buffer[..]);
         match Frame::parse(&mut cursor) {
             Ok(frame) => {
@@ -77,19 +170,40 @@ impl Connection {
                 }
                 Ok(Some(frame))
             }
-            Err(err) => {
-                if err.kind() == ErrorKind::InvalidData || cursor.get_ref().len() == BUFFER_SIZE {
-                    self.buffer.clear();
-                    Err(err)
-                } else {
-                    Ok(None)
-                }
+            Err(err) if err.kind() == ErrorKind::InvalidData => {
+                self.buffer.clear();
+                Err(err)
             }
+            Err(_) => Ok(None),
         }
     }
 
     pub async fn write_frame(&mut self, frame: &Frame) -> Result<()> {
-        self.stream.write_all(frame.as_bytes().as_slice()).await?;
+        self.write_frames(std::slice::from_ref(frame)).await
+    }
+
+    /// Writes several frames as a single transport write and flush, so a
+    /// batch of pipelined responses does not pay one flush per command.
+    pub async fn write_frames(&mut self, frames: &[Frame]) -> Result<()> {
+        let mut bytes = Vec::new();
+        for frame in frames {
+            bytes.extend(frame.as_bytes());
+        }
+        let out = match self.cipher.as_mut() {
+            Some(cipher) => cipher.seal(&bytes),
+            None => bytes,
+        };
+        self.stream.write_all(&out).await?;
         self.stream.flush().await
     }
 }
+
+/// Compares two byte strings without short-circuiting on the first
+/// mismatching byte, so a timing side channel cannot be used to guess
+/// `--requirepass` one byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
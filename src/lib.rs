@@ -1,31 +1,37 @@
 mod command;
 mod connection;
+mod crypto;
 mod frame;
+pub mod quic;
 pub mod snowflake;
+pub mod udp;
+pub mod ws;
 
 use crate::command::Command;
 use crate::connection::Connection;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::watch::Receiver;
 
 #[derive(Debug)]
-pub struct Handler {
-    conn: Connection,
+pub struct Handler<S> {
+    conn: Connection<S>,
     closing: Receiver<()>,
     _closed: UnboundedSender<()>,
 }
 
-impl Handler {
+impl<S: AsyncRead + AsyncWrite + Unpin> Handler<S> {
     pub fn new(
-        socket: TcpStream,
+        stream: S,
         addr: SocketAddr,
+        requirepass: Option<Arc<str>>,
         closing: Receiver<()>,
         closed: UnboundedSender<()>,
-    ) -> Handler {
+    ) -> Handler<S> {
         Handler {
-            conn: Connection::new(socket, addr),
+            conn: Connection::new(stream, addr, requirepass),
             closing,
             _closed: closed,
         }
@@ -35,16 +41,22 @@ impl Handler {
         let mut shutting_down = false;
         let mut client_closed = false;
         while !shutting_down && !client_closed {
-            if let Some(frame) = tokio::select! {
+            let Some(frame) = (tokio::select! {
                 frame = self.conn.read_frame() => frame?,
                 _ = self.closing.changed() => { shutting_down = true; None },
-            } {
-                let cmd = Command::from_frame(frame)?;
-                let resp = cmd.apply(&mut self.conn).await;
-                self.conn.write_frame(&resp).await?;
-            } else {
+            }) else {
                 client_closed = true;
+                continue;
+            };
+
+            // Drain every pipelined command already sitting in the buffer
+            // before going back to the socket, and flush their responses
+            // together in a single write.
+            let mut responses = vec![Command::from_frame(frame)?.apply(&mut self.conn).await];
+            while let Some(frame) = self.conn.try_parse_frame()? {
+                responses.push(Command::from_frame(frame)?.apply(&mut self.conn).await);
             }
+            self.conn.write_frames(&responses).await?;
         }
         Ok(())
     }
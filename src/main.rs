@@ -1,13 +1,20 @@
 use clap::Parser;
+use redflake::quic::QuicStream;
 use redflake::snowflake::MACHINE;
+use redflake::udp;
+use redflake::ws::WsStream;
 use redflake::Handler;
+use std::fs::File;
+use std::io::BufReader;
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, UdpSocket};
 use tokio::signal::ctrl_c;
 use tokio::sync::{mpsc, watch, Semaphore};
 use tokio::time::timeout;
+use tokio_rustls::TlsAcceptor;
 use tracing::{error, info, warn};
 use tracing_subscriber::fmt;
 
@@ -17,6 +24,21 @@ struct CmdArgs {
     /// Server port
     #[arg(short, long, default_value_t = 6380)]
     port: u16,
+    /// WebSocket server port; unset disables the WebSocket listener
+    #[arg(long)]
+    ws_port: Option<u16>,
+    /// PEM-encoded TLS certificate chain; terminates `--port` in TLS instead of plaintext
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+    /// PEM-encoded TLS private key matching `--tls-cert`
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+    /// QUIC server port; requires `--tls-cert`/`--tls-key`
+    #[arg(long, requires_all = ["tls_cert", "tls_key"])]
+    quic_port: Option<u16>,
+    /// Shared secret clients must provide via `AUTH` before issuing `NEXT`
+    #[arg(long)]
+    requirepass: Option<String>,
     /// Machine ID
     #[arg(short, long, default_value_t = 0)]
     machine: u8,
@@ -25,6 +47,27 @@ struct CmdArgs {
     max_clients: usize,
 }
 
+/// Loads a certificate chain and private key from disk and builds the
+/// rustls server configuration shared by the TLS and QUIC handshakes.
+fn load_tls_config(cert_path: &PathBuf, key_path: &PathBuf) -> Arc<rustls::ServerConfig> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(
+        File::open(cert_path).expect("Unable to open TLS certificate file"),
+    ))
+    .collect::<std::result::Result<Vec<_>, _>>()
+    .expect("Unable to parse TLS certificate chain");
+    let key = rustls_pemfile::private_key(&mut BufReader::new(
+        File::open(key_path).expect("Unable to open TLS key file"),
+    ))
+    .expect("Unable to parse TLS private key")
+    .expect("No private key found in TLS key file");
+
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .expect("Invalid TLS certificate/key pair");
+    Arc::new(config)
+}
+
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     // parse command-line arguments
@@ -44,23 +87,192 @@ async fn main() {
         args.machine, args.port
     );
 
+    let requirepass: Option<Arc<str>> = args.requirepass.clone().map(Arc::from);
+
+    // The UDP mode has no per-packet authentication, so serving it
+    // alongside `--requirepass` would let anyone reach the ID generator
+    // over a side channel that bypasses AUTH entirely.
+    let udp_socket = if requirepass.is_none() {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, args.port))
+            .await
+            .expect("Unable to bind UDP socket");
+        Some(socket)
+    } else {
+        warn!("UDP listener disabled: it has no authentication and --requirepass is set");
+        None
+    };
+
+    let ws_listener = if let Some(ws_port) = args.ws_port {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, ws_port))
+            .await
+            .expect("Unable to bind WebSocket socket");
+        info!("Machine ID({}) started to listen for WebSocket clients on {}", args.machine, ws_port);
+        Some(listener)
+    } else {
+        None
+    };
+
+    let tls_config = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => {
+            info!("Machine ID({}) terminating {} with TLS", args.machine, args.port);
+            Some(load_tls_config(cert, key))
+        }
+        _ => None,
+    };
+    let tls_acceptor = tls_config.clone().map(TlsAcceptor::from);
+
+    let quic_endpoint = if let Some(quic_port) = args.quic_port {
+        let server_config = quinn::ServerConfig::with_crypto(Arc::new(
+            quinn::crypto::rustls::QuicServerConfig::try_from((*tls_config.clone().unwrap()).clone())
+                .expect("Unable to build QUIC TLS config"),
+        ));
+        let endpoint = quinn::Endpoint::server(
+            server_config,
+            (Ipv4Addr::UNSPECIFIED, quic_port).into(),
+        )
+        .expect("Unable to bind QUIC socket");
+        info!("Machine ID({}) started to listen for QUIC clients on {}", args.machine, quic_port);
+        Some(endpoint)
+    } else {
+        None
+    };
+
     let semaphore = Arc::new(Semaphore::new(args.max_clients));
     let (closing_tx, _) = watch::channel(());
     let (conn_closed_tx, mut all_conn_closed_rx) = mpsc::unbounded_channel::<()>();
 
     tokio::select! {
-        // handle connection
+        // handle connection, TLS-terminated when `--tls-cert`/`--tls-key` are set
         _ = async {
             loop {
                 let permit = semaphore.clone().acquire_owned().await.unwrap();
                 let (socket, addr) = listener.accept().await.expect("Failed to accept socket");
-                let mut handler = Handler::new(socket, addr, closing_tx.subscribe(), conn_closed_tx.clone());
-                tokio::spawn(async move {
-                    if let Err(err) = handler.handle().await {
-                        error!(cause = ?err, "Error while processing connection");
+                let closing = closing_tx.subscribe();
+                let closed = conn_closed_tx.clone();
+                let requirepass = requirepass.clone();
+                match &tls_acceptor {
+                    Some(acceptor) => {
+                        let acceptor = acceptor.clone();
+                        tokio::spawn(async move {
+                            let socket = match acceptor.accept(socket).await {
+                                Ok(socket) => socket,
+                                Err(err) => {
+                                    error!(cause = ?err, "Error while performing TLS handshake");
+                                    drop(permit);
+                                    return;
+                                }
+                            };
+                            let mut handler = Handler::new(socket, addr, requirepass, closing, closed);
+                            if let Err(err) = handler.handle().await {
+                                error!(cause = ?err, "Error while processing connection");
+                            }
+                            drop(permit);
+                        });
+                    }
+                    None => {
+                        let mut handler = Handler::new(socket, addr, requirepass, closing, closed);
+                        tokio::spawn(async move {
+                            if let Err(err) = handler.handle().await {
+                                error!(cause = ?err, "Error while processing connection");
+                            }
+                            drop(permit);
+                        });
+                    }
+                }
+            }
+        } => {},
+        // handle QUIC connections, if enabled
+        _ = async {
+            match &quic_endpoint {
+                Some(endpoint) => loop {
+                    let Some(incoming) = endpoint.accept().await else { break };
+                    let closing_tx = closing_tx.clone();
+                    let conn_closed_tx = conn_closed_tx.clone();
+                    let semaphore = semaphore.clone();
+                    let requirepass = requirepass.clone();
+                    tokio::spawn(async move {
+                        let connection = match incoming.await {
+                            Ok(connection) => connection,
+                            Err(err) => {
+                                error!(cause = ?err, "Error while performing QUIC handshake");
+                                return;
+                            }
+                        };
+                        let addr = connection.remote_address();
+                        let mut closing = closing_tx.subscribe();
+                        loop {
+                            let (send, recv) = tokio::select! {
+                                stream = connection.accept_bi() => match stream {
+                                    Ok(stream) => stream,
+                                    Err(_) => break,
+                                },
+                                _ = closing.changed() => break,
+                            };
+                            let permit = semaphore.clone().acquire_owned().await.unwrap();
+                            let mut handler = Handler::new(QuicStream::new(send, recv), addr, requirepass.clone(), closing_tx.subscribe(), conn_closed_tx.clone());
+                            tokio::spawn(async move {
+                                if let Err(err) = handler.handle().await {
+                                    error!(cause = ?err, "Error while processing connection");
+                                }
+                                drop(permit);
+                            });
+                        }
+                    });
+                },
+                None => std::future::pending().await,
+            }
+        } => {},
+        // handle WebSocket connections, if enabled
+        _ = async {
+            match &ws_listener {
+                Some(listener) => loop {
+                    let permit = semaphore.clone().acquire_owned().await.unwrap();
+                    let (socket, addr) = listener.accept().await.expect("Failed to accept WebSocket socket");
+                    let closing = closing_tx.subscribe();
+                    let closed = conn_closed_tx.clone();
+                    let requirepass = requirepass.clone();
+                    tokio::spawn(async move {
+                        let upgraded = match tokio_tungstenite::accept_async(socket).await {
+                            Ok(upgraded) => upgraded,
+                            Err(err) => {
+                                error!(cause = ?err, "Error while upgrading WebSocket connection");
+                                drop(permit);
+                                return;
+                            }
+                        };
+                        let mut handler = Handler::new(WsStream::new(upgraded), addr, requirepass, closing, closed);
+                        if let Err(err) = handler.handle().await {
+                            error!(cause = ?err, "Error while processing connection");
+                        }
+                        drop(permit);
+                    });
+                },
+                None => std::future::pending().await,
+            }
+        } => {},
+        // handle connectionless UDP requests, if enabled
+        _ = async {
+            match &udp_socket {
+                Some(udp_socket) => {
+                    let mut buf = [0u8; 16];
+                    loop {
+                        let (len, addr) = match udp_socket.recv_from(&mut buf).await {
+                            Ok(result) => result,
+                            Err(err) => {
+                                error!(cause = ?err, "Error while reading UDP datagram");
+                                continue;
+                            }
+                        };
+                        let Some((nonce, packet)) = udp::parse(&buf[..len]) else {
+                            continue;
+                        };
+                        let reply = udp::handle(nonce, packet);
+                        if let Err(err) = udp_socket.send_to(&reply, addr).await {
+                            error!(cause = ?err, "Error while sending UDP reply");
+                        }
                     }
-                    drop(permit);
-                });
+                }
+                None => std::future::pending().await,
             }
         } => {},
         // handle shutdown
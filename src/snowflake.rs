@@ -48,6 +48,71 @@ pub fn next_id() -> Result<i64, String> {
     }
 }
 
+/// Largest `count` a single `NEXT` batch request may reserve. Capped well
+/// below the full sequence space of one millisecond (rather than at
+/// `MAX_SEQ + 1`) so a large batch always has room to land: a ceiling equal
+/// to the whole millisecond would let a big enough batch spin indefinitely
+/// waiting for a millisecond that concurrent single-ID traffic never
+/// entirely vacates, since every busy millisecond leaves it short by at
+/// least one slot.
+pub const MAX_BATCH: u16 = (MAX_SEQ + 1) / 2;
+
+/// Reserves `count` contiguous sequence slots and returns their IDs in
+/// increasing order. Spins until a millisecond has enough slots left for the
+/// whole batch, so the result is always a single contiguous block.
+pub fn next_ids(count: u16) -> Result<Vec<i64>, String> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if count > MAX_BATCH {
+        return Err(format!(
+            "Batch size {} exceeds maximum of {}",
+            count, MAX_BATCH
+        ));
+    }
+    loop {
+        let last_id = LAST_ID.load(Ordering::Acquire);
+        let id = SnowflakeId::from(last_id);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+            - EPOCH;
+        if now < id.timestamp {
+            return Err("Clock moved backwards".to_string());
+        }
+        let start = if now == id.timestamp { id.sequence + 1 } else { 0 };
+        if start as u32 + count as u32 > MAX_SEQ as u32 + 1 {
+            // Not enough room left in this millisecond for the whole batch;
+            // spin until the clock advances and the sequence resets to 0.
+            spin_loop();
+            continue;
+        }
+        let machine = *MACHINE.get().unwrap();
+        let next_last_id = i64::from(&SnowflakeId {
+            timestamp: now,
+            machine,
+            sequence: start + count - 1,
+        });
+        if LAST_ID
+            .compare_exchange_weak(last_id, next_last_id, Ordering::Release, Ordering::Relaxed)
+            .is_ok()
+        {
+            return Ok((start..start + count)
+                .map(|sequence| {
+                    i64::from(&SnowflakeId {
+                        timestamp: now,
+                        machine,
+                        sequence,
+                    })
+                })
+                .collect());
+        } else {
+            spin_loop();
+        }
+    }
+}
+
 pub struct SnowflakeId {
     timestamp: u64,
     machine: u8,
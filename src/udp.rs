@@ -0,0 +1,63 @@
+//! Binary packet format for the connectionless UDP mode. Unlike the RESP
+//! transports there is no per-connection state, so `HELLO`/`AUTH` do not
+//! apply here: a datagram in gets an ID (or a batch of IDs) right back out.
+
+use crate::snowflake::next_id;
+
+/// Largest batch a single `NEXT-BATCH` datagram may request, to keep the
+/// reply from growing into an oversized UDP packet.
+pub const MAX_BATCH: u16 = 512;
+
+const OP_NEXT: u8 = 0x01;
+const OP_NEXT_BATCH: u8 = 0x02;
+
+const NONCE_LEN: usize = 4;
+
+#[derive(Debug)]
+pub enum Packet {
+    Next,
+    NextBatch(u16),
+}
+
+/// Parses a request datagram into the client-supplied nonce and the decoded
+/// packet, or `None` if the datagram is malformed.
+pub fn parse(buf: &[u8]) -> Option<([u8; NONCE_LEN], Packet)> {
+    if buf.len() < 1 + NONCE_LEN {
+        return None;
+    }
+    let opcode = buf[0];
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(&buf[1..1 + NONCE_LEN]);
+
+    match opcode {
+        OP_NEXT => Some((nonce, Packet::Next)),
+        OP_NEXT_BATCH => {
+            let rest = &buf[1 + NONCE_LEN..];
+            if rest.len() < 2 {
+                return None;
+            }
+            let count = u16::from_be_bytes([rest[0], rest[1]]).min(MAX_BATCH).max(1);
+            Some((nonce, Packet::NextBatch(count)))
+        }
+        _ => None,
+    }
+}
+
+/// Builds a reply datagram: the echoed nonce followed by one or more 8-byte
+/// big-endian IDs. IDs that failed to allocate (e.g. the clock moved
+/// backwards) are simply omitted from the reply.
+pub fn handle(nonce: [u8; NONCE_LEN], packet: Packet) -> Vec<u8> {
+    let count = match packet {
+        Packet::Next => 1,
+        Packet::NextBatch(count) => count,
+    };
+
+    let mut reply = Vec::with_capacity(NONCE_LEN + count as usize * 8);
+    reply.extend_from_slice(&nonce);
+    for _ in 0..count {
+        if let Ok(id) = next_id() {
+            reply.extend_from_slice(&id.to_be_bytes());
+        }
+    }
+    reply
+}
@@ -0,0 +1,86 @@
+use bytes::{Buf, BytesMut};
+use futures_util::{Sink, Stream};
+use std::io::{Error, ErrorKind, Result};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Adapts an upgraded WebSocket connection into `AsyncRead + AsyncWrite` so
+/// it can be handed to `Connection`/`Handler` exactly like a raw `TcpStream`.
+/// Binary WebSocket messages carry the RESP bytes; any other message type is
+/// ignored.
+pub struct WsStream {
+    inner: WebSocketStream<TcpStream>,
+    read_buf: BytesMut,
+}
+
+impl WsStream {
+    pub fn new(inner: WebSocketStream<TcpStream>) -> WsStream {
+        WsStream {
+            inner,
+            read_buf: BytesMut::new(),
+        }
+    }
+}
+
+impl AsyncRead for WsStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        loop {
+            if !self.read_buf.is_empty() {
+                let n = std::cmp::min(buf.remaining(), self.read_buf.len());
+                buf.put_slice(&self.read_buf[..n]);
+                self.read_buf.advance(n);
+                return Poll::Ready(Ok(()));
+            }
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(Message::Binary(data)))) => {
+                    self.read_buf.extend_from_slice(&data);
+                }
+                Poll::Ready(Some(Ok(_))) => continue,
+                Poll::Ready(Some(Err(err))) => {
+                    return Poll::Ready(Err(Error::new(ErrorKind::Other, err)))
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for WsStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        match Pin::new(&mut self.inner).poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                match Pin::new(&mut self.inner).start_send(Message::Binary(buf.to_vec())) {
+                    Ok(()) => Poll::Ready(Ok(buf.len())),
+                    Err(err) => Poll::Ready(Err(Error::new(ErrorKind::Other, err))),
+                }
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(Error::new(ErrorKind::Other, err))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner)
+            .poll_flush(cx)
+            .map_err(|err| Error::new(ErrorKind::Other, err))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.inner)
+            .poll_close(cx)
+            .map_err(|err| Error::new(ErrorKind::Other, err))
+    }
+}